@@ -0,0 +1,76 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Nova DRM driver core.
+//!
+//! Nova binds as an auxiliary driver on top of `nova-core`, which owns the
+//! PCI device and hands us the BAR0 and BAR1 mappings. The GSP firmware is
+//! booted through SEC2 (see [`crate::falcon`]) and then driven through the
+//! GSP RPC channel set up here; VRAM allocation, copy-engine migration and
+//! performance counters are handled by Nova directly.
+
+use kernel::auxiliary;
+use kernel::drm::ioctl;
+use kernel::prelude::*;
+use kernel::sync::{Arc, Mutex};
+
+use crate::ce::CopyEngine;
+use crate::file::File;
+use crate::gem::VramAllocator;
+use crate::gsp::Gsp;
+use crate::pm::CounterSet;
+use crate::uapi::{DrmNovaGemCreate, DrmNovaGemInfo, DrmNovaGemMmap, DrmNovaPmQuery};
+
+/// Name of the signed GSP firmware image SEC2 verifies before booting it.
+const GSP_FW_NAME: &CStr = kernel::c_str!("nvidia/nova/gsp.bin");
+
+kernel::drm_ioctl_table! {
+    (NOVA_GEM_CREATE, DrmNovaGemCreate, ioctl::render_allow(), File::gem_create),
+    (NOVA_GEM_INFO, DrmNovaGemInfo, ioctl::render_allow(), File::gem_info),
+    (NOVA_GEM_MMAP, DrmNovaGemMmap, ioctl::render_allow(), File::gem_mmap),
+    (NOVA_PM_QUERY, DrmNovaPmQuery, ioctl::render_allow(), File::pm_query),
+}
+
+/// Driver-wide state shared across the `drm::Device` and all open files.
+pub(crate) struct Nova {
+    pub(crate) gsp: Gsp,
+    /// Allocator over the PCI BAR-mapped framebuffer aperture backing VRAM
+    /// GEM objects.
+    pub(crate) vram: Mutex<VramAllocator>,
+    /// Copy-engine channel used to migrate GEM objects between VRAM and GTT.
+    pub(crate) ce: Arc<CopyEngine>,
+    /// Hardware performance-counter domains, read back through
+    /// `DRM_IOCTL_NOVA_PM_QUERY`.
+    pub(crate) pm: CounterSet,
+}
+
+/// The Nova auxiliary driver.
+pub(crate) struct NovaDriver {
+    pub(crate) data: Arc<Nova>,
+}
+
+impl auxiliary::Driver for NovaDriver {
+    type IdInfo = ();
+
+    fn probe(adev: &auxiliary::Device, _id_info: &Self::IdInfo) -> Result<Pin<KBox<Self>>> {
+        let bar0 = adev.iomap(0)?;
+
+        // Bring the GSP out of reset via SEC2's secure-boot sequence before
+        // the RPC channel below talks to it.
+        crate::falcon::boot_gsp(adev.as_ref(), &bar0, GSP_FW_NAME)?;
+
+        let gsp = Gsp::new(adev.as_ref(), bar0)?;
+        gsp.init()?;
+
+        // BAR1 is the linear aperture onto VRAM that GEM objects are
+        // carved out of.
+        let fb_bar = adev.iomap(1)?;
+        let vram = Mutex::new(VramAllocator::new(fb_bar.size() as u64)?);
+
+        let ce = CopyEngine::new(adev.as_ref())?;
+        let pm = CounterSet::new()?;
+
+        let data = Arc::new(Nova { gsp, vram, ce, pm }, GFP_KERNEL)?;
+
+        KBox::new(NovaDriver { data }, GFP_KERNEL).map(Pin::from)
+    }
+}
@@ -0,0 +1,225 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Falcon/SEC2 secure-boot loader for signed GSP firmware.
+//!
+//! Before the command/message rings in [`crate::gsp`] can be used, the GSP
+//! processor itself has to be brought up, and on GSP-enabled chips that
+//! means running it through the SEC2 falcon's light-secure-boot sequence
+//! first: SEC2 validates the signed GSP firmware image and only then
+//! releases the GSP out of reset to run it. This mirrors the booter
+//! sequence Nouveau runs on gv100/tu102/ga102 before handing control to the
+//! GSP RPC channel.
+//!
+//! The signed image is requested from the firmware loader, DMA-mapped so
+//! the falcon's own DMA engine can pull it in, and the falcon is kicked off
+//! by writing its boot vector and setting `CPUCTL_STARTCPU`. We then poll
+//! the mailbox register SEC2 writes its verification result to.
+
+use core::time::Duration;
+
+use kernel::device::Device;
+use kernel::dma::CoherentAllocation;
+use kernel::firmware::Firmware;
+use kernel::io::mem::IoMem;
+use kernel::prelude::*;
+use kernel::time::Instant;
+
+/// Byte offset of the SEC2 falcon's register block from the start of BAR0.
+const SEC2_BASE: usize = 0x840000;
+
+/// Falcon CPU control register: bit 1 starts the CPU, bit 4 reads back set
+/// once it has halted.
+const FALCON_CPUCTL: usize = SEC2_BASE + 0x100;
+const CPUCTL_STARTCPU: u32 = 1 << 1;
+const CPUCTL_HALTED: u32 = 1 << 4;
+
+/// Boot vector: IMEM offset the falcon starts executing from once started.
+const FALCON_BOOTVEC: usize = SEC2_BASE + 0x104;
+
+/// DMA source address (low/high halves) the falcon's block-transfer engine
+/// reads the firmware image from.
+const FALCON_DMATRFBASE: usize = SEC2_BASE + 0x110;
+const FALCON_DMATRFBASE1: usize = SEC2_BASE + 0x128;
+
+/// Byte offset into the image (relative to `FALCON_DMATRFBASE`) the current
+/// block transfer reads from.
+const FALCON_DMATRFFBOFFS: usize = SEC2_BASE + 0x114;
+/// IMEM/DMEM offset the current block transfer writes to.
+const FALCON_DMATRFMOFFS: usize = SEC2_BASE + 0x11c;
+/// Kicks off one `DMA_BLOCK_SIZE` block transfer; bit 4 selects IMEM over
+/// DMEM, bit 1 reads back set once the engine is idle again.
+const FALCON_DMATRFCMD: usize = SEC2_BASE + 0x118;
+const DMATRFCMD_IMEM: u32 = 1 << 4;
+const DMATRFCMD_IDLE: u32 = 1 << 1;
+
+/// Size of a single block-transfer unit; code/data segments are transferred
+/// one block at a time, same as Nouveau's falcon DMA loader.
+const DMA_BLOCK_SIZE: u32 = 256;
+
+/// Mailbox SEC2 writes its verification result to before halting.
+const FALCON_MAILBOX0: usize = SEC2_BASE + 0x040;
+const SEC2_VERIFY_OK: u32 = 0;
+
+/// How long to wait for SEC2 to finish verifying and booting the image.
+const BOOT_TIMEOUT: Duration = Duration::from_millis(500);
+/// How long to wait for a single block transfer to drain.
+const DMA_TIMEOUT: Duration = Duration::from_millis(50);
+const POLL_INTERVAL: Duration = Duration::from_micros(50);
+
+/// Header prefixed to the signed GSP firmware blob.
+///
+/// `code_off`/`code_size` and `data_off`/`data_size` locate the falcon
+/// IMEM/DMEM images within the blob; the rest of the blob is the signature
+/// SEC2 checks before releasing the GSP from reset.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SignedFwHeader {
+    magic: u32,
+    code_off: u32,
+    code_size: u32,
+    data_off: u32,
+    data_size: u32,
+}
+
+const FW_MAGIC: u32 = 0x5446_4753; // "SGFT"
+
+/// Requests the named signed firmware image, DMA-maps it, and runs it
+/// through the SEC2 falcon's secure-boot sequence.
+///
+/// On success SEC2 has verified the image's signature and released the GSP
+/// out of reset; the caller can proceed to set up the [`crate::gsp`] RPC
+/// channel. Returns [`EINVAL`] if the image is malformed and [`ETIMEDOUT`]
+/// if SEC2 doesn't finish booting within [`BOOT_TIMEOUT`].
+pub(crate) fn boot_gsp(dev: &Device, bar0: &IoMem, fw_name: &CStr) -> Result {
+    let fw = Firmware::request(fw_name, dev)?;
+    let data = fw.data();
+
+    if data.len() < core::mem::size_of::<SignedFwHeader>() {
+        return Err(EINVAL);
+    }
+
+    // SAFETY: `data` was just checked to be at least `size_of::<SignedFwHeader>()`
+    // bytes long, and the header has no alignment requirements beyond `u32`.
+    let header = unsafe { core::ptr::read_unaligned(data.as_ptr() as *const SignedFwHeader) };
+    if header.magic != FW_MAGIC {
+        return Err(EINVAL);
+    }
+
+    let image_end = header
+        .code_off
+        .max(header.data_off)
+        .checked_add(header.code_size.max(header.data_size))
+        .ok_or(EINVAL)?;
+    if (image_end as usize) > data.len() {
+        return Err(EINVAL);
+    }
+
+    let dma = copy_to_coherent(dev, data)?;
+
+    load_and_start(bar0, &dma, &header)?;
+    wait_for_boot(bar0)
+}
+
+/// Copies the firmware image into a DMA-coherent allocation the falcon's
+/// block-transfer engine can read from.
+fn copy_to_coherent(dev: &Device, data: &[u8]) -> Result<CoherentAllocation<u8>> {
+    let dma = CoherentAllocation::alloc_coherent(dev, data.len(), GFP_KERNEL, true)?;
+
+    // SAFETY: `dma` was just allocated with `data.len()` bytes.
+    unsafe { dma.write_bytes(data, 0) };
+
+    Ok(dma)
+}
+
+/// Points the falcon's DMA engine at the image, transfers the code and data
+/// segments into IMEM/DMEM starting at offset 0 in each, and releases the
+/// CPU to start executing from the start of IMEM.
+fn load_and_start(bar0: &IoMem, dma: &CoherentAllocation<u8>, header: &SignedFwHeader) -> Result {
+    let base = dma.dma_handle();
+
+    // SAFETY: `FALCON_DMATRFBASE`/`FALCON_DMATRFBASE1` are within the BAR0
+    // mapping; the falcon's DMA engine only reads from them once a transfer
+    // is kicked off via `FALCON_DMATRFCMD` below.
+    unsafe {
+        bar0.writel(base as u32, FALCON_DMATRFBASE);
+        bar0.writel((base >> 32) as u32, FALCON_DMATRFBASE1);
+    }
+
+    transfer_segment(bar0, header.code_off, header.code_size, true)?;
+    transfer_segment(bar0, header.data_off, header.data_size, false)?;
+
+    // SAFETY: `FALCON_BOOTVEC` is within the BAR0 mapping.
+    unsafe { bar0.writel(0, FALCON_BOOTVEC) };
+
+    // SAFETY: `FALCON_CPUCTL` is within the BAR0 mapping; setting
+    // `CPUCTL_STARTCPU` releases the falcon to start executing at the
+    // boot vector just programmed.
+    unsafe { bar0.writel(CPUCTL_STARTCPU, FALCON_CPUCTL) };
+
+    Ok(())
+}
+
+/// Transfers `size` bytes starting at `fb_off` (relative to
+/// `FALCON_DMATRFBASE`) into IMEM (`imem == true`) or DMEM, one
+/// [`DMA_BLOCK_SIZE`] block at a time, landing each block at the matching
+/// offset from the start of the target memory.
+fn transfer_segment(bar0: &IoMem, fb_off: u32, size: u32, imem: bool) -> Result {
+    let mut done = 0;
+    while done < size {
+        let block = core::cmp::min(DMA_BLOCK_SIZE, size - done);
+        let cmd = if imem { DMATRFCMD_IMEM } else { 0 };
+
+        // SAFETY: `FALCON_DMATRFFBOFFS`/`FALCON_DMATRFMOFFS`/
+        // `FALCON_DMATRFCMD` are within the BAR0 mapping.
+        unsafe {
+            bar0.writel(fb_off + done, FALCON_DMATRFFBOFFS);
+            bar0.writel(done, FALCON_DMATRFMOFFS);
+            bar0.writel(cmd, FALCON_DMATRFCMD);
+        }
+
+        wait_for_block(bar0)?;
+        done += block;
+    }
+
+    Ok(())
+}
+
+/// Polls until the in-flight block transfer drains or [`DMA_TIMEOUT`]
+/// elapses.
+fn wait_for_block(bar0: &IoMem) -> Result {
+    let deadline = Instant::now() + DMA_TIMEOUT;
+
+    loop {
+        // SAFETY: `FALCON_DMATRFCMD` is within the BAR0 mapping.
+        let cmd = unsafe { bar0.readl(FALCON_DMATRFCMD) };
+        if cmd & DMATRFCMD_IDLE != 0 {
+            return Ok(());
+        }
+
+        if Instant::now() >= deadline {
+            return Err(ETIMEDOUT);
+        }
+        kernel::time::delay::fsleep(POLL_INTERVAL);
+    }
+}
+
+/// Polls until the falcon halts and reports its verification result in the
+/// mailbox register, or [`BOOT_TIMEOUT`] elapses.
+fn wait_for_boot(bar0: &IoMem) -> Result {
+    let deadline = Instant::now() + BOOT_TIMEOUT;
+
+    loop {
+        // SAFETY: `FALCON_CPUCTL` is within the BAR0 mapping.
+        let cpuctl = unsafe { bar0.readl(FALCON_CPUCTL) };
+        if cpuctl & CPUCTL_HALTED != 0 {
+            // SAFETY: `FALCON_MAILBOX0` is within the BAR0 mapping.
+            let result = unsafe { bar0.readl(FALCON_MAILBOX0) };
+            return if result == SEC2_VERIFY_OK { Ok(()) } else { Err(EIO) };
+        }
+
+        if Instant::now() >= deadline {
+            return Err(ETIMEDOUT);
+        }
+        kernel::time::delay::fsleep(POLL_INTERVAL);
+    }
+}
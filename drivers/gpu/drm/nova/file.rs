@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Per-`open()` DRM file state and ioctl handlers.
+
+use kernel::drm::gem::BaseObject;
+use kernel::prelude::*;
+use kernel::sync::Arc;
+use kernel::time::Instant;
+
+use crate::driver::Nova;
+use crate::gem::{NovaObject, Object, Placement};
+use crate::uapi::{
+    DrmNovaGemCreate, DrmNovaGemInfo, DrmNovaGemMmap, DrmNovaPmQuery, NOVA_PLACEMENT_GTT,
+    NOVA_PLACEMENT_VRAM, NOVA_PM_MAX_DOMAINS,
+};
+
+/// State tracked for each userspace file descriptor opened against the DRM
+/// device.
+pub(crate) struct File {
+    dev: Arc<Nova>,
+}
+
+impl File {
+    pub(crate) fn open(dev: &Arc<Nova>) -> Result<Self> {
+        Ok(File { dev: dev.clone() })
+    }
+
+    /// `DRM_IOCTL_NOVA_GEM_CREATE`.
+    pub(crate) fn gem_create(&self, args: &mut DrmNovaGemCreate) -> Result {
+        let placement = match args.placement {
+            NOVA_PLACEMENT_VRAM => Placement::Vram,
+            NOVA_PLACEMENT_GTT => Placement::Gtt,
+            _ => return Err(EINVAL),
+        };
+
+        let obj = NovaObject::new(&self.dev, args.size, placement)?;
+        args.handle = obj.create_handle(self)?;
+
+        Ok(())
+    }
+
+    /// `DRM_IOCTL_NOVA_GEM_INFO`.
+    pub(crate) fn gem_info(&self, args: &mut DrmNovaGemInfo) -> Result {
+        let obj = Object::lookup_handle(self, args.handle)?;
+
+        args.size = obj.size;
+        args.placement = match obj.placement {
+            Placement::Vram => NOVA_PLACEMENT_VRAM,
+            Placement::Gtt => NOVA_PLACEMENT_GTT,
+        };
+
+        Ok(())
+    }
+
+    /// `DRM_IOCTL_NOVA_GEM_MMAP`.
+    pub(crate) fn gem_mmap(&self, args: &mut DrmNovaGemMmap) -> Result {
+        let obj = Object::lookup_handle(self, args.handle)?;
+
+        args.offset = obj.create_mmap_offset()?;
+
+        Ok(())
+    }
+
+    /// `DRM_IOCTL_NOVA_PM_QUERY`.
+    pub(crate) fn pm_query(&self, args: &mut DrmNovaPmQuery) -> Result {
+        let count = args.count as usize;
+        if count > NOVA_PM_MAX_DOMAINS {
+            return Err(EINVAL);
+        }
+
+        let bar0 = self.dev.gsp.bar0();
+        self.dev.pm.start(bar0)?;
+
+        // Always stop the counters again before returning, even if the
+        // read itself failed, so a query never leaves them free-running.
+        let result = self.dev.pm.read(bar0, &args.domains[..count]);
+        self.dev.pm.stop(bar0)?;
+        let values = result?;
+
+        args.values[..count].copy_from_slice(&values);
+        args.timestamp_ns = Instant::now().as_nanos() as u64;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,439 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! GSP RPC command/message queue.
+//!
+//! Nova is a GSP-only driver: essentially all hardware state is owned by the
+//! GPU System Processor firmware, and the kernel driver only ever talks to it
+//! through a pair of ring buffers living in a DMA-coherent region shared with
+//! the GSP. This module implements that channel: a command queue (CPU -> GSP)
+//! and a message queue (GSP -> CPU), each framed by a [`QueueHeader`] holding
+//! the read/write offsets that the two sides advance independently.
+//!
+//! To issue an RPC the CPU writes a [`MsgHeader`] followed by the request
+//! payload at the command queue's write offset (wrapping at the end of the
+//! ring as needed), advances the offset, and rings the GSP doorbell. The GSP
+//! answers by appending a response to the message queue and advancing its own
+//! write offset; the CPU matches the response to the request by sequence
+//! number.
+
+use core::mem::{size_of, MaybeUninit};
+use core::time::Duration;
+
+use kernel::dma::CoherentAllocation;
+use kernel::device::Device;
+use kernel::io::mem::IoMem;
+use kernel::prelude::*;
+use kernel::sync::Mutex;
+use kernel::time::Instant;
+
+/// Size in bytes of each ring, header included.
+///
+/// The GSP firmware expects this to be a fixed size agreed upon at channel
+/// setup time; we use the same default as the command/message queues on
+/// other GSP-based drivers.
+const QUEUE_SIZE: usize = 0x4_0000;
+
+/// Byte offset of the GSP doorbell register from the start of the mapped
+/// BAR0 region.
+const DOORBELL_REG: usize = 0xb80000;
+
+/// How long to wait for a response before giving up on an RPC.
+const RPC_TIMEOUT: Duration = Duration::from_millis(2000);
+
+/// How long to sleep between polls of the message queue.
+const POLL_INTERVAL: Duration = Duration::from_micros(100);
+
+/// Header of each of the two rings, stored at the very start of the
+/// DMA-coherent allocation backing it.
+///
+/// `write_off` is only ever written by the producer of the ring and
+/// `read_off` only by the consumer, so the two sides never contend on the
+/// same field.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct QueueHeader {
+    write_off: u32,
+    read_off: u32,
+}
+
+/// Header prefixed to every message placed on the wire, command or
+/// response alike.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct MsgHeader {
+    /// RPC function id.
+    function: u32,
+    /// Total length of the payload following this header, in bytes.
+    length: u32,
+    /// Sequence number used to match a response to its request.
+    seqno: u32,
+}
+
+const HEADER_SIZE: usize = size_of::<QueueHeader>();
+const MSG_HEADER_SIZE: usize = size_of::<MsgHeader>();
+const RING_CAPACITY: usize = QUEUE_SIZE - HEADER_SIZE;
+
+/// Payload bytes actually usable per ring.
+///
+/// One byte of `RING_CAPACITY` is kept permanently unusable so a completely
+/// full ring never advances `write_off` all the way back onto `read_off` --
+/// if it did, [`Ring::header`] readers couldn't tell a full ring from an
+/// empty one, since both look like `write_off == read_off`.
+const USABLE_CAPACITY: usize = RING_CAPACITY - 1;
+
+/// Bytes currently occupied between `read_off` and `write_off` in a ring of
+/// `RING_CAPACITY` bytes. Pure offset arithmetic, split out so it can be
+/// unit-tested without a DMA-backed [`Ring`].
+fn ring_used(write_off: u32, read_off: u32) -> usize {
+    (write_off as usize + RING_CAPACITY - read_off as usize) % RING_CAPACITY
+}
+
+/// Usable payload bytes still free: `USABLE_CAPACITY` minus the bytes
+/// currently occupied.
+fn ring_free(write_off: u32, read_off: u32) -> usize {
+    USABLE_CAPACITY - ring_used(write_off, read_off)
+}
+
+/// Splits a `len`-byte region starting at `off` within a `RING_CAPACITY`-
+/// sized payload area into the run of bytes before the wrap and the bytes
+/// that wrap around to the start, if any. Pure offset arithmetic, split out
+/// so it can be unit-tested without a DMA-backed [`Ring`].
+fn wrap_split(off: usize, len: usize) -> (usize, usize) {
+    let first = core::cmp::min(len, RING_CAPACITY - off);
+    (first, len - first)
+}
+
+/// A single direction ring buffer in GSP shared memory.
+struct Ring {
+    mem: CoherentAllocation<u8>,
+}
+
+impl Ring {
+    fn new(dev: &Device, coherent_dma: bool) -> Result<Self> {
+        let mem = CoherentAllocation::alloc_coherent(dev, QUEUE_SIZE, GFP_KERNEL, coherent_dma)?;
+
+        // SAFETY: `mem` was just allocated and zeroed by the DMA API; writing
+        // an all-zero header back is a no-op kept here for documentation.
+        let header = QueueHeader { write_off: 0, read_off: 0 };
+        let ring = Ring { mem };
+        ring.write_header(&header);
+
+        Ok(ring)
+    }
+
+    fn header(&self) -> QueueHeader {
+        // SAFETY: the header lives at offset 0 of `self.mem`, which is at
+        // least `QUEUE_SIZE` bytes and was allocated by us.
+        unsafe { self.mem.read(0) }
+    }
+
+    fn write_header(&self, header: &QueueHeader) {
+        // SAFETY: same allocation as above.
+        unsafe { self.mem.write(header, 0) };
+    }
+
+    /// Number of usable payload bytes currently free in the ring.
+    fn free_space(&self, header: &QueueHeader) -> usize {
+        ring_free(header.write_off, header.read_off)
+    }
+
+    /// Copies `bytes` into the ring starting at `off`, wrapping around the
+    /// end of the payload area as needed.
+    fn copy_in(&self, off: u32, bytes: &[u8]) {
+        let off = off as usize;
+        let (first, _) = wrap_split(off, bytes.len());
+
+        // SAFETY: `HEADER_SIZE + off` and `HEADER_SIZE + off + first` both
+        // stay within the `QUEUE_SIZE`-byte allocation because `off <
+        // RING_CAPACITY` and `first <= RING_CAPACITY - off`.
+        unsafe { self.mem.write_bytes(&bytes[..first], HEADER_SIZE + off) };
+
+        if first < bytes.len() {
+            // Wrapped: the remainder lands at the start of the payload area.
+            // SAFETY: `bytes.len() - first <= off`, so this stays in bounds.
+            unsafe { self.mem.write_bytes(&bytes[first..], HEADER_SIZE) };
+        }
+    }
+
+    /// Reads `len` payload bytes starting at `off`, wrapping around the end
+    /// of the payload area as needed.
+    fn copy_out(&self, off: u32, len: usize) -> KVec<u8> {
+        let off = off as usize;
+        let mut out = KVec::with_capacity(len, GFP_KERNEL).unwrap_or_else(|_| KVec::new());
+        let (first, _) = wrap_split(off, len);
+
+        // SAFETY: see `copy_in`.
+        unsafe { self.mem.read_bytes(HEADER_SIZE + off, first, &mut out) };
+        if first < len {
+            // SAFETY: see `copy_in`.
+            unsafe { self.mem.read_bytes(HEADER_SIZE, len - first, &mut out) };
+        }
+
+        out
+    }
+}
+
+/// The bidirectional RPC channel to the GSP firmware.
+///
+/// Owns the command (CPU -> GSP) and message (GSP -> CPU) rings and the
+/// BAR0 mapping used to ring the GSP doorbell. `seqno` is monotonically
+/// increasing and shared by both channels so every in-flight request has a
+/// unique id to match its response against. `rpc_lock` serializes
+/// [`Gsp::send_rpc`] end-to-end: [`Gsp::wait_for_reply`] drops any reply
+/// whose `seqno` doesn't match the one it's waiting for, so two overlapping
+/// RPCs would end up stealing each other's responses. Only one RPC may be
+/// outstanding at a time until that's fixed.
+pub(crate) struct Gsp {
+    cmdq: Mutex<Ring>,
+    msgq: Mutex<Ring>,
+    bar0: IoMem,
+    seqno: Mutex<u32>,
+    rpc_lock: Mutex<()>,
+}
+
+impl Gsp {
+    /// Allocates the command/message rings and binds the doorbell register.
+    pub(crate) fn new(dev: &Device, bar0: IoMem) -> Result<Self> {
+        Ok(Gsp {
+            cmdq: Mutex::new(Ring::new(dev, true)?),
+            msgq: Mutex::new(Ring::new(dev, true)?),
+            bar0,
+            seqno: Mutex::new(0),
+            rpc_lock: Mutex::new(()),
+        })
+    }
+
+    /// The BAR0 mapping backing this channel, for other subsystems (e.g.
+    /// [`crate::pm`]) that need to reach registers outside the doorbell.
+    pub(crate) fn bar0(&self) -> &IoMem {
+        &self.bar0
+    }
+
+    fn next_seqno(&self) -> u32 {
+        let mut seqno = self.seqno.lock();
+        *seqno = seqno.wrapping_add(1);
+        *seqno
+    }
+
+    fn ring_doorbell(&self) {
+        // SAFETY: `DOORBELL_REG` is within the BAR0 mapping established at
+        // probe time; any value rings the bell, the GSP ignores the payload.
+        unsafe { self.bar0.writel(1, DOORBELL_REG) };
+    }
+
+    /// Writes `function`/`payload` onto the command queue, blocking briefly
+    /// if there isn't enough free space for the GSP to have drained the
+    /// queue yet, then rings the doorbell.
+    fn submit(&self, function: u32, seqno: u32, payload: &[u8]) -> Result {
+        let needed = MSG_HEADER_SIZE + payload.len();
+        if needed > USABLE_CAPACITY {
+            return Err(EINVAL);
+        }
+
+        let deadline = Instant::now() + RPC_TIMEOUT;
+        let cmdq = self.cmdq.lock();
+        loop {
+            let header = cmdq.header();
+            if cmdq.free_space(&header) >= needed {
+                let msg_header = MsgHeader { function, length: payload.len() as u32, seqno };
+
+                // SAFETY: `msg_header` is `#[repr(C)]` and `MSG_HEADER_SIZE`
+                // bytes long by construction.
+                let header_bytes = unsafe {
+                    core::slice::from_raw_parts(
+                        &msg_header as *const MsgHeader as *const u8,
+                        MSG_HEADER_SIZE,
+                    )
+                };
+
+                cmdq.copy_in(header.write_off, header_bytes);
+                let payload_off = (header.write_off as usize + MSG_HEADER_SIZE) % RING_CAPACITY;
+                cmdq.copy_in(payload_off as u32, payload);
+
+                let new_write_off =
+                    (header.write_off as usize + needed) % RING_CAPACITY;
+                cmdq.write_header(&QueueHeader {
+                    write_off: new_write_off as u32,
+                    read_off: header.read_off,
+                });
+
+                self.ring_doorbell();
+                return Ok(());
+            }
+
+            // Back-pressure: the GSP hasn't drained enough of the command
+            // queue yet. Back off and retry until it catches up or we time
+            // out; the queue must never be allowed to overflow.
+            if Instant::now() >= deadline {
+                return Err(EAGAIN);
+            }
+            kernel::time::delay::fsleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Polls the message queue until a response with `seqno` arrives,
+    /// returning its payload bytes.
+    fn wait_for_reply(&self, seqno: u32, expected_len: usize) -> Result<KVec<u8>> {
+        let deadline = Instant::now() + RPC_TIMEOUT;
+
+        loop {
+            let msgq = self.msgq.lock();
+            let header = msgq.header();
+
+            if header.write_off != header.read_off {
+                let header_bytes = msgq.copy_out(header.read_off, MSG_HEADER_SIZE);
+                // SAFETY: `header_bytes.len() == MSG_HEADER_SIZE` and
+                // `MsgHeader` is POD.
+                let msg_header: MsgHeader = unsafe {
+                    core::ptr::read_unaligned(header_bytes.as_ptr() as *const MsgHeader)
+                };
+
+                // `msg_header.length` comes straight from the GSP-written
+                // queue; validate it against what the queue actually holds
+                // before trusting it to size the `copy_out` below, otherwise
+                // a corrupt or malicious length wraps past the end of the
+                // message and reads out of the DMA allocation's bounds.
+                let msg_len = msg_header.length as usize;
+                let available = ring_used(header.write_off, header.read_off)
+                    .checked_sub(MSG_HEADER_SIZE)
+                    .ok_or(EIO)?;
+                if msg_len > available || msg_len > USABLE_CAPACITY {
+                    return Err(EIO);
+                }
+
+                let payload_off =
+                    (header.read_off as usize + MSG_HEADER_SIZE) % RING_CAPACITY;
+                let payload = msgq.copy_out(payload_off as u32, msg_len);
+
+                let new_read_off =
+                    (header.read_off as usize + MSG_HEADER_SIZE + msg_len) % RING_CAPACITY;
+                msgq.write_header(&QueueHeader {
+                    write_off: header.write_off,
+                    read_off: new_read_off as u32,
+                });
+
+                if msg_header.seqno == seqno {
+                    if payload.len() != expected_len {
+                        return Err(EIO);
+                    }
+                    return Ok(payload);
+                }
+                // Stale response to an RPC we've already given up on; drop
+                // it and keep looking for ours.
+                continue;
+            }
+
+            drop(msgq);
+            if Instant::now() >= deadline {
+                return Err(ETIMEDOUT);
+            }
+            kernel::time::delay::fsleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Issues an RPC of function `function` carrying `req` and returns the
+    /// firmware's typed response.
+    ///
+    /// `Req` and `Resp` must be plain, `#[repr(C)]` data types matching the
+    /// wire layout the GSP expects for `function`. At most one call runs at
+    /// a time, held for the full request/response round trip by
+    /// `rpc_lock` -- see the note on [`Gsp`] for why.
+    pub(crate) fn send_rpc<Req: Copy, Resp: Copy>(&self, function: u32, req: &Req) -> Result<Resp> {
+        let _rpc_guard = self.rpc_lock.lock();
+
+        let seqno = self.next_seqno();
+
+        // SAFETY: `Req` is a plain `#[repr(C)]` type owned by the caller.
+        let req_bytes = unsafe {
+            core::slice::from_raw_parts(req as *const Req as *const u8, size_of::<Req>())
+        };
+
+        self.submit(function, seqno, req_bytes)?;
+        let resp_bytes = self.wait_for_reply(seqno, size_of::<Resp>())?;
+
+        let mut resp = MaybeUninit::<Resp>::zeroed();
+        // SAFETY: `resp_bytes` holds exactly `size_of::<Resp>()` bytes, and
+        // `Resp: Copy` guarantees it is safe to reinterpret as such.
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                resp_bytes.as_ptr(),
+                resp.as_mut_ptr() as *mut u8,
+                size_of::<Resp>(),
+            );
+            Ok(resp.assume_init())
+        }
+    }
+
+    /// Issues the initial handshake RPC that brings the GSP up once the
+    /// command/message rings are mapped, called by
+    /// [`crate::driver::NovaDriver::probe`] right after [`Gsp::new`].
+    ///
+    /// Returns [`EIO`] if the GSP reports it didn't come up cleanly.
+    pub(crate) fn init(&self) -> Result {
+        let req = InitReq { ring_size: QUEUE_SIZE as u32 };
+        let resp: InitResp = self.send_rpc(RPC_FN_INIT, &req)?;
+
+        if resp.status != 0 {
+            return Err(EIO);
+        }
+
+        Ok(())
+    }
+}
+
+/// RPC function implementing the initial handshake issued once the
+/// command/message rings are up.
+const RPC_FN_INIT: u32 = 0;
+
+/// Request for [`RPC_FN_INIT`]: tells the GSP the ring size Nova set both
+/// queues up with.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct InitReq {
+    ring_size: u32,
+}
+
+/// Response to [`RPC_FN_INIT`]: `status == 0` means the GSP came up clean.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct InitResp {
+    status: u32,
+}
+
+#[kunit_tests(rust_nova_gsp)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_used_handles_wrapped_write() {
+        // write has wrapped behind read: the occupied run is read..CAP plus
+        // 0..write, i.e. RING_CAPACITY - 90 bytes.
+        assert_eq!(ring_used(10, 100), RING_CAPACITY - 90);
+    }
+
+    #[test]
+    fn ring_free_reports_full_capacity_when_empty() {
+        assert_eq!(ring_used(5, 5), 0);
+        assert_eq!(ring_free(5, 5), USABLE_CAPACITY);
+    }
+
+    #[test]
+    fn ring_free_never_lets_a_full_ring_alias_empty() {
+        // Filling the ring with exactly USABLE_CAPACITY bytes must leave
+        // write_off one byte behind read_off, never equal to it.
+        let read = 0u32;
+        let write = USABLE_CAPACITY as u32;
+        assert_ne!(write, read);
+        assert_eq!(ring_free(write, read), 0);
+    }
+
+    #[test]
+    fn wrap_split_without_wrap() {
+        assert_eq!(wrap_split(0, 10), (10, 0));
+    }
+
+    #[test]
+    fn wrap_split_with_wrap() {
+        assert_eq!(wrap_split(RING_CAPACITY - 5, 10), (5, 5));
+    }
+}
@@ -2,9 +2,13 @@
 
 //! Nova DRM Driver
 
+mod ce;
 mod driver;
+mod falcon;
 mod file;
 mod gem;
+mod gsp;
+mod pm;
 mod uapi;
 
 use crate::driver::NovaDriver;
@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Hardware performance-counter readout.
+//!
+//! Mirrors Nouveau's PM engine: the GPU exposes a handful of free-running
+//! hardware counters grouped into domains (shader/memory/engine
+//! utilization, ...), each counting a chip-specific signal. Nova doesn't
+//! try to be as exhaustive as Nouveau's per-chip signal tables; it exposes
+//! the domains every chip Nova supports has in common and lets userspace
+//! profilers read their accumulated values through
+//! `DRM_IOCTL_NOVA_PM_QUERY`.
+//!
+//! Counters free-run once started; callers latch a snapshot by calling
+//! [`CounterSet::read`], which returns each domain's value as of the latch
+//! without resetting it, so successive reads give a running total a
+//! profiler can diff itself.
+
+use core::time::Duration;
+
+use kernel::io::mem::IoMem;
+use kernel::prelude::*;
+use kernel::sync::Mutex;
+
+use crate::uapi::{NOVA_PM_DOMAIN_ENGINE, NOVA_PM_DOMAIN_MEMORY, NOVA_PM_DOMAIN_SHADER};
+
+/// Byte offset of the PM engine's register block from the start of BAR0.
+const PM_BASE: usize = 0x1b0000;
+
+/// Control register: bit 0 starts the counters free-running, clearing it
+/// stops and holds their current value.
+const PM_CTRL: usize = PM_BASE + 0x000;
+const PM_CTRL_RUN: u32 = 1 << 0;
+
+/// Latch register: writing any value copies the live counters into the
+/// per-domain snapshot registers below, atomically with respect to the
+/// counters still incrementing.
+const PM_LATCH: usize = PM_BASE + 0x004;
+
+/// Per-domain snapshot counter registers, indexed by domain.
+const PM_COUNTER: [usize; 3] = [PM_BASE + 0x100, PM_BASE + 0x104, PM_BASE + 0x108];
+
+/// Fixed settle delay between latching and reading the snapshot registers;
+/// this generation doesn't expose a "latch done" status bit, so a fixed
+/// delay is what Nouveau uses too.
+const LATCH_SETTLE: Duration = Duration::from_micros(50);
+
+/// A single counter domain and the BAR0 register its latched snapshot
+/// shows up in.
+#[derive(Clone, Copy)]
+struct Domain {
+    id: u32,
+    register: usize,
+}
+
+/// The set of performance-counter domains available on this chip.
+pub(crate) struct CounterSet {
+    domains: KVec<Domain>,
+    running: Mutex<bool>,
+}
+
+impl CounterSet {
+    /// Builds the counter set for the domains every chip Nova supports has:
+    /// shader, memory and engine utilization.
+    pub(crate) fn new() -> Result<Self> {
+        let mut domains = KVec::new();
+        domains.push(Domain { id: NOVA_PM_DOMAIN_SHADER, register: PM_COUNTER[0] }, GFP_KERNEL)?;
+        domains.push(Domain { id: NOVA_PM_DOMAIN_MEMORY, register: PM_COUNTER[1] }, GFP_KERNEL)?;
+        domains.push(Domain { id: NOVA_PM_DOMAIN_ENGINE, register: PM_COUNTER[2] }, GFP_KERNEL)?;
+
+        Ok(CounterSet { domains, running: Mutex::new(false) })
+    }
+
+    /// Starts the hardware counters free-running. A no-op if already
+    /// started.
+    pub(crate) fn start(&self, bar0: &IoMem) -> Result {
+        let mut running = self.running.lock();
+        if !*running {
+            // SAFETY: `PM_CTRL` is within the BAR0 mapping.
+            unsafe { bar0.writel(PM_CTRL_RUN, PM_CTRL) };
+            *running = true;
+        }
+        Ok(())
+    }
+
+    /// Stops the hardware counters, holding their last value.
+    pub(crate) fn stop(&self, bar0: &IoMem) -> Result {
+        let mut running = self.running.lock();
+        if *running {
+            // SAFETY: `PM_CTRL` is within the BAR0 mapping.
+            unsafe { bar0.writel(0, PM_CTRL) };
+            *running = false;
+        }
+        Ok(())
+    }
+
+    /// Latches a snapshot of the requested domains and returns their
+    /// accumulated values, in the same order as `ids`.
+    ///
+    /// Returns [`EINVAL`] for any id that isn't a domain this chip exposes.
+    pub(crate) fn read(&self, bar0: &IoMem, ids: &[u32]) -> Result<KVec<u64>> {
+        // SAFETY: `PM_LATCH` is within the BAR0 mapping; any value latches.
+        unsafe { bar0.writel(1, PM_LATCH) };
+        kernel::time::delay::fsleep(LATCH_SETTLE);
+
+        let mut values = KVec::with_capacity(ids.len(), GFP_KERNEL)?;
+        for &id in ids {
+            let domain = self.domains.iter().find(|d| d.id == id).ok_or(EINVAL)?;
+            // SAFETY: `domain.register` is within the BAR0 mapping.
+            let value = unsafe { bar0.readl(domain.register) };
+            values.push(value as u64, GFP_KERNEL)?;
+        }
+
+        Ok(values)
+    }
+}
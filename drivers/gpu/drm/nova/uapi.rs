@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Userspace ABI.
+
+/// Object placement, as requested by `DRM_IOCTL_NOVA_GEM_CREATE` and
+/// reported back by `DRM_IOCTL_NOVA_GEM_INFO`.
+pub(crate) const NOVA_PLACEMENT_VRAM: u32 = 0;
+pub(crate) const NOVA_PLACEMENT_GTT: u32 = 1;
+
+/// Argument to `DRM_IOCTL_NOVA_GEM_CREATE`.
+///
+/// Allocates a new buffer object of `size` bytes at `placement` and returns
+/// a handle for it.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct DrmNovaGemCreate {
+    pub(crate) size: u64,
+    pub(crate) placement: u32,
+    pub(crate) handle: u32,
+}
+
+/// Argument to `DRM_IOCTL_NOVA_GEM_INFO`.
+///
+/// Queries the size and placement of an already allocated object.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct DrmNovaGemInfo {
+    pub(crate) handle: u32,
+    pub(crate) placement: u32,
+    pub(crate) size: u64,
+}
+
+/// Argument to `DRM_IOCTL_NOVA_GEM_MMAP`.
+///
+/// Returns a fake offset for use with `mmap(2)` on the DRM device fd, which
+/// `drm_gem_mmap()` resolves back to the object's pages.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct DrmNovaGemMmap {
+    pub(crate) handle: u32,
+    pub(crate) pad: u32,
+    pub(crate) offset: u64,
+}
+
+/// Performance-counter domain ids, as requested by
+/// `DRM_IOCTL_NOVA_PM_QUERY`.
+pub(crate) const NOVA_PM_DOMAIN_SHADER: u32 = 0;
+pub(crate) const NOVA_PM_DOMAIN_MEMORY: u32 = 1;
+pub(crate) const NOVA_PM_DOMAIN_ENGINE: u32 = 2;
+
+/// Maximum number of domains that can be requested in a single
+/// `DRM_IOCTL_NOVA_PM_QUERY` call.
+pub(crate) const NOVA_PM_MAX_DOMAINS: usize = 8;
+
+/// Argument to `DRM_IOCTL_NOVA_PM_QUERY`.
+///
+/// `domains[..count]` holds the domain ids to sample; on return
+/// `values[..count]` holds each domain's accumulated counter value as of
+/// `timestamp_ns`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct DrmNovaPmQuery {
+    pub(crate) domains: [u32; NOVA_PM_MAX_DOMAINS],
+    pub(crate) values: [u64; NOVA_PM_MAX_DOMAINS],
+    pub(crate) count: u32,
+    pub(crate) timestamp_ns: u64,
+}